@@ -0,0 +1,179 @@
+//! Loadable server configuration: a bind address, optional TLS material, and a
+//! mapping from `Host` header values to independently configured document roots.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use crate::Args;
+
+/// Per-host serving options.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HostConfig {
+    /// Path to the document root served for this host.
+    pub document_root: PathBuf,
+
+    /// Generate an HTML directory listing for directories without an index file.
+    #[serde(default)]
+    pub autoindex: bool,
+
+    /// Render `.md` files as HTML instead of serving them raw.
+    #[serde(default)]
+    pub markdown: bool,
+
+    /// Filename to look for when a request resolves to a directory.
+    #[serde(default = "default_index")]
+    pub index: String,
+}
+
+fn default_index() -> String {
+    "index.html".to_owned()
+}
+
+/// Top-level server configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// Address to bind the listener to.
+    pub bind: SocketAddr,
+
+    /// PEM-encoded TLS certificate chain; serves plain HTTP when absent.
+    #[serde(default)]
+    pub tls_cert: Option<PathBuf>,
+
+    /// PEM-encoded TLS private key; serves plain HTTP when absent.
+    #[serde(default)]
+    pub tls_key: Option<PathBuf>,
+
+    /// Document roots keyed by the `Host` header value they serve.
+    #[serde(default)]
+    pub hosts: HashMap<String, HostConfig>,
+
+    /// Host key to fall back to when the request's `Host` doesn't match.
+    #[serde(default)]
+    pub default_host: Option<String>,
+
+    /// Record client IP addresses in access log lines.
+    #[serde(default = "default_log_ips")]
+    pub log_ips: bool,
+
+    /// Cap the number of requests served concurrently.
+    #[serde(default)]
+    pub max_connections: Option<usize>,
+
+    /// Respond `503 Service Unavailable` instead of queueing once
+    /// `max_connections` is reached.
+    #[serde(default)]
+    pub reject_when_full: bool,
+}
+
+fn default_log_ips() -> bool {
+    true
+}
+
+impl Config {
+    /// Loads a configuration file, inferring JSON or YAML from its extension.
+    pub async fn load(path: &Path) -> Result<Config, Box<dyn std::error::Error>> {
+        let contents = tokio::fs::read_to_string(path).await?;
+
+        let config = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&contents)?,
+            _ => serde_json::from_str(&contents)?,
+        };
+
+        Ok(config)
+    }
+
+    /// Synthesizes a single-host configuration from the convenience command-line flags.
+    pub fn from_args(args: &Args) -> Config {
+        const DEFAULT_HOST: &str = "";
+
+        let mut hosts = HashMap::new();
+        hosts.insert(
+            DEFAULT_HOST.to_owned(),
+            HostConfig {
+                document_root: args.document_root.clone(),
+                autoindex: args.autoindex,
+                markdown: args.markdown,
+                index: default_index(),
+            },
+        );
+
+        Config {
+            bind: args.bind,
+            tls_cert: args.tls_cert.clone(),
+            tls_key: args.tls_key.clone(),
+            hosts,
+            default_host: Some(DEFAULT_HOST.to_owned()),
+            log_ips: !args.no_log_ips,
+            max_connections: args.max_connections,
+            reject_when_full: args.reject_when_full,
+        }
+    }
+
+    /// Selects the host config matching `host`, falling back to `default_host`.
+    pub fn resolve(&self, host: Option<&str>) -> Option<&HostConfig> {
+        host.and_then(|host| self.hosts.get(host))
+            .or_else(|| self.hosts.get(self.default_host.as_deref()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(document_root: &str) -> HostConfig {
+        HostConfig {
+            document_root: PathBuf::from(document_root),
+            autoindex: false,
+            markdown: false,
+            index: default_index(),
+        }
+    }
+
+    fn config(hosts: HashMap<String, HostConfig>, default_host: Option<&str>) -> Config {
+        Config {
+            bind: "127.0.0.1:0".parse().unwrap(),
+            tls_cert: None,
+            tls_key: None,
+            hosts,
+            default_host: default_host.map(str::to_owned),
+            log_ips: true,
+            max_connections: None,
+            reject_when_full: false,
+        }
+    }
+
+    #[test]
+    fn resolve_matches_exact_host() {
+        let mut hosts = HashMap::new();
+        hosts.insert("example.com".to_owned(), host("/www/example"));
+        hosts.insert("other.example".to_owned(), host("/www/other"));
+        let config = config(hosts, None);
+
+        let resolved = config.resolve(Some("example.com")).unwrap();
+        assert_eq!(resolved.document_root, PathBuf::from("/www/example"));
+    }
+
+    #[test]
+    fn resolve_falls_back_to_default_host() {
+        let mut hosts = HashMap::new();
+        hosts.insert("_default".to_owned(), host("/www/default"));
+        let config = config(hosts, Some("_default"));
+
+        let resolved = config.resolve(Some("unknown.example")).unwrap();
+        assert_eq!(resolved.document_root, PathBuf::from("/www/default"));
+
+        let resolved = config.resolve(None).unwrap();
+        assert_eq!(resolved.document_root, PathBuf::from("/www/default"));
+    }
+
+    #[test]
+    fn resolve_returns_none_without_match_or_default() {
+        let mut hosts = HashMap::new();
+        hosts.insert("example.com".to_owned(), host("/www/example"));
+        let config = config(hosts, None);
+
+        assert!(config.resolve(Some("unknown.example")).is_none());
+    }
+}
@@ -1,9 +1,12 @@
+mod config;
+
 use clap::Parser;
+use config::Config;
 use hyper::http::{Error as HttpError, Method, Request, Response, StatusCode};
 use hyper::service::{make_service_fn, service_fn};
 use hyper::{Body, Error, Server};
-use log::info;
-use std::path::PathBuf;
+use log::{error, info};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 #[derive(Debug, Parser)]
@@ -16,23 +19,94 @@ struct Args {
     /// Set the path of the document root.
     #[clap(short = 'r', long, value_name = "PATH", default_value = ".")]
     document_root: PathBuf,
+
+    /// Serve over TLS using this PEM-encoded certificate chain. Requires `--tls-key`.
+    #[clap(long, value_name = "PATH", requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+
+    /// Serve over TLS using this PEM-encoded private key. Requires `--tls-cert`.
+    #[clap(long, value_name = "PATH", requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+
+    /// Generate an HTML directory listing for directories without an `index.html`.
+    #[clap(long)]
+    autoindex: bool,
+
+    /// Render `.md` files as HTML instead of serving them raw.
+    #[clap(long)]
+    markdown: bool,
+
+    /// Load a JSON or YAML config mapping `Host` headers to document roots,
+    /// instead of the single root set by `--document-root`.
+    #[clap(short, long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Record client IP addresses in access log lines (default).
+    #[clap(long, conflicts_with = "no_log_ips")]
+    log_ips: bool,
+
+    /// Omit client IP addresses from access log lines, for privacy.
+    #[clap(long, conflicts_with = "log_ips")]
+    no_log_ips: bool,
+
+    /// Cap the number of requests served concurrently.
+    #[clap(long, value_name = "N")]
+    max_connections: Option<usize>,
+
+    /// Respond `503 Service Unavailable` instead of queueing once
+    /// `--max-connections` is reached.
+    #[clap(long, requires = "max_connections")]
+    reject_when_full: bool,
+}
+
+/// Runtime state shared across connections: the resolved configuration plus
+/// the semaphore that bounds concurrently in-flight requests.
+struct State {
+    config: Config,
+    semaphore: Option<Arc<tokio::sync::Semaphore>>,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    use simplelog::{Config, SimpleLogger};
+    use simplelog::SimpleLogger;
 
-    SimpleLogger::init(log::LevelFilter::Info, Config::default())
+    SimpleLogger::init(log::LevelFilter::Info, simplelog::Config::default())
         .expect("failed to initialize logging");
 
-    let args = Arc::new(Args::parse());
+    let args = Args::parse();
+    let config = match &args.config {
+        Some(path) => Config::load(path).await?,
+        None => Config::from_args(&args),
+    };
+    let semaphore = config
+        .max_connections
+        .map(|n| Arc::new(tokio::sync::Semaphore::new(n)));
+    let state = Arc::new(State { config, semaphore });
+
+    match (&state.config.tls_cert, &state.config.tls_key) {
+        (Some(cert), Some(key)) => {
+            let (cert, key) = (cert.clone(), key.clone());
+            serve_tls(state, &cert, &key).await
+        }
+        _ => serve_plain(state).await,
+    }
+}
+
+async fn serve_plain(state: Arc<State>) -> Result<(), Box<dyn std::error::Error>> {
+    use hyper::server::conn::AddrStream;
 
-    let service = make_service_fn(|_| {
-        let args = Arc::clone(&args);
-        async { Ok::<_, Error>(service_fn(move |req| handle(Arc::clone(&args), req))) }
+    let bind = state.config.bind;
+    let service = make_service_fn(|conn: &AddrStream| {
+        let state = Arc::clone(&state);
+        let remote_addr = conn.remote_addr();
+        async move {
+            Ok::<_, Error>(service_fn(move |req| {
+                handle(Arc::clone(&state), remote_addr, req)
+            }))
+        }
     });
 
-    let server = Server::try_bind(&args.bind)?.serve(service);
+    let server = Server::try_bind(&bind)?.serve(service);
     info!("Server started on {}", server.local_addr());
 
     tokio::select! {
@@ -43,6 +117,77 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+async fn serve_tls(
+    state: Arc<State>,
+    cert: &Path,
+    key: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use hyper::server::conn::Http;
+    use tokio::net::TcpListener;
+    use tokio_rustls::TlsAcceptor;
+
+    let tls_config = load_tls_config(cert, key)?;
+    let acceptor = TlsAcceptor::from(Arc::new(tls_config));
+
+    let listener = TcpListener::bind(&state.config.bind).await?;
+    info!("Server started on {}", listener.local_addr()?);
+
+    loop {
+        let (stream, remote_addr) = tokio::select! {
+            res = listener.accept() => res?,
+            r = handle_signal() => return r.map_err(Into::into),
+        };
+
+        let acceptor = acceptor.clone();
+        let state = Arc::clone(&state);
+
+        tokio::spawn(async move {
+            let stream = match acceptor.accept(stream).await {
+                Ok(stream) => stream,
+                Err(error) => {
+                    error!("TLS handshake failed: {}", error);
+                    return;
+                }
+            };
+
+            let service = service_fn(move |req| handle(Arc::clone(&state), remote_addr, req));
+            if let Err(error) = Http::new().serve_connection(stream, service).await {
+                error!("error serving connection: {}", error);
+            }
+        });
+    }
+}
+
+/// Builds a TLS server configuration from a PEM certificate chain and private key,
+/// advertising both `h2` and `http/1.1` via ALPN.
+fn load_tls_config(
+    cert: &Path,
+    key: &Path,
+) -> Result<tokio_rustls::rustls::ServerConfig, Box<dyn std::error::Error>> {
+    use std::fs::File;
+    use std::io::BufReader;
+    use tokio_rustls::rustls::{Certificate, PrivateKey, ServerConfig};
+
+    let cert_chain = rustls_pemfile::certs(&mut BufReader::new(File::open(cert)?))?
+        .into_iter()
+        .map(Certificate)
+        .collect();
+
+    let mut keys = rustls_pemfile::pkcs8_private_keys(&mut BufReader::new(File::open(key)?))?;
+    let key = PrivateKey(
+        keys.pop()
+            .ok_or("no private key found in --tls-key file")?,
+    );
+
+    let mut config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(cert_chain, key)?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(config)
+}
+
 #[cfg(unix)]
 async fn handle_signal() -> Result<(), std::io::Error> {
     use tokio::signal::unix::{signal, SignalKind};
@@ -63,28 +208,459 @@ async fn handle_signal() -> Result<(), std::io::Error> {
     Ok(tokio::signal::ctrl_c().await?)
 }
 
-async fn handle(args: Arc<Args>, req: Request<Body>) -> Result<Response<Body>, HttpError> {
+/// Serves a single request, then logs it in a combined-log-like format:
+/// client address, method, path, status, response size, and latency.
+async fn handle(
+    state: Arc<State>,
+    remote_addr: std::net::SocketAddr,
+    req: Request<Body>,
+) -> Result<Response<Body>, HttpError> {
+    let start = std::time::Instant::now();
+    let method = req.method().clone();
+    let path = req.uri().path().to_owned();
+
+    let mut rejected = false;
+    let permit = match &state.semaphore {
+        Some(semaphore) if state.config.reject_when_full => {
+            match Arc::clone(semaphore).try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(_) => {
+                    rejected = true;
+                    None
+                }
+            }
+        }
+        Some(semaphore) => Some(
+            Arc::clone(semaphore)
+                .acquire_owned()
+                .await
+                .expect("semaphore is never closed"),
+        ),
+        None => None,
+    };
+
+    let response = if rejected {
+        Response::builder()
+            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .body(Body::empty())
+    } else {
+        respond(Arc::clone(&state), req).await
+    };
+    let elapsed = start.elapsed();
+
+    let client = if state.config.log_ips {
+        remote_addr.ip().to_string()
+    } else {
+        "-".to_owned()
+    };
+    let status = response
+        .as_ref()
+        .map(|res| res.status())
+        .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let bytes = response
+        .as_ref()
+        .ok()
+        .and_then(|res| res.headers().get(hyper::header::CONTENT_LENGTH))
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("-");
+
+    info!(
+        "{} \"{} {}\" {} {} {}ms",
+        client,
+        method,
+        path,
+        status.as_u16(),
+        bytes,
+        elapsed.as_millis()
+    );
+
+    // Hold the permit until the body stream itself is exhausted or dropped,
+    // not just until the response headers are built: the body may still be
+    // reading the file (or compressing it) well after `respond` returns.
+    response.map(|res| {
+        res.map(|body| {
+            Body::wrap_stream(PermitGuardedBody {
+                inner: body,
+                _permit: permit,
+            })
+        })
+    })
+}
+
+/// Wraps a response body so a connection-limiting semaphore permit is held
+/// for as long as the body is being streamed, releasing it only once the
+/// body is exhausted or dropped.
+struct PermitGuardedBody {
+    inner: Body,
+    _permit: Option<tokio::sync::OwnedSemaphorePermit>,
+}
+
+impl futures_core::Stream for PermitGuardedBody {
+    type Item = Result<hyper::body::Bytes, hyper::Error>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        std::pin::Pin::new(&mut self.inner).poll_next(cx)
+    }
+}
+
+async fn respond(state: Arc<State>, req: Request<Body>) -> Result<Response<Body>, HttpError> {
+    use hyper::header::{ACCEPT_ENCODING, HOST, RANGE};
     use tokio::fs::File;
+    use tokio::io::{AsyncReadExt, AsyncSeekExt, BufReader};
     use tokio_util::codec::{BytesCodec, FramedRead};
 
-    let mut path = args.document_root.join(normalize(req.uri().path()));
+    if req.method() != Method::GET {
+        return Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .body(Body::empty());
+    }
+
+    let host = req
+        .headers()
+        .get(HOST)
+        .and_then(|value| value.to_str().ok())
+        .map(strip_port);
+
+    let host = match state.config.resolve(host) {
+        Some(host) => host,
+        None => {
+            return Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::empty())
+        }
+    };
+
+    let mut path = host.document_root.join(normalize(req.uri().path()));
     if path.is_dir() {
-        path.push("index.html");
+        let index = path.join(&host.index);
+        if index.is_file() {
+            path = index;
+        } else if host.autoindex {
+            return render_autoindex(&path, req.uri().path()).await;
+        } else {
+            return Response::builder()
+                .status(StatusCode::FORBIDDEN)
+                .body(Body::empty());
+        }
+    }
+
+    if host.markdown && path.extension().is_some_and(|ext| ext == "md") {
+        return render_markdown(&path).await;
     }
 
-    let res = if req.method() != Method::GET {
-        Err(StatusCode::METHOD_NOT_ALLOWED)
-    } else if path.is_dir() {
-        Err(StatusCode::FORBIDDEN)
-    } else if let Ok(file) = File::open(path).await {
-        Ok(Body::wrap_stream(FramedRead::new(file, BytesCodec::new())))
+    let content_type = mime_guess::from_path(&path).first_or_octet_stream();
+
+    let mut file = match File::open(path).await {
+        Ok(file) => file,
+        Err(_) => {
+            return Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::empty())
+        }
+    };
+
+    let len = match file.metadata().await {
+        Ok(metadata) => metadata.len(),
+        Err(_) => {
+            return Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::empty())
+        }
+    };
+
+    let range = req
+        .headers()
+        .get(RANGE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| parse_range(value, len))
+        .unwrap_or(Range::Full);
+
+    match range {
+        Range::Satisfiable { start, end } => {
+            if let Err(error) = file.seek(std::io::SeekFrom::Start(start)).await {
+                return Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .body(Body::from(error.to_string()));
+            }
+
+            let body = Body::wrap_stream(FramedRead::new(
+                file.take(end - start + 1),
+                BytesCodec::new(),
+            ));
+
+            Response::builder()
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header("Accept-Ranges", "bytes")
+                .header("Content-Range", format!("bytes {}-{}/{}", start, end, len))
+                .header("Content-Length", end - start + 1)
+                .header("Content-Type", content_type.as_ref())
+                .body(body)
+        }
+        Range::Unsatisfiable => Response::builder()
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header("Content-Range", format!("bytes */{}", len))
+            .body(Body::empty()),
+        Range::Full => {
+            let encoding = if should_compress(&content_type) {
+                req.headers()
+                    .get(ACCEPT_ENCODING)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(negotiate_encoding)
+            } else {
+                None
+            };
+
+            let mut builder = Response::builder()
+                .header("Accept-Ranges", "bytes")
+                .header("Content-Type", content_type.as_ref())
+                .header("Vary", "Accept-Encoding");
+
+            let body = match encoding {
+                Some(Encoding::Gzip) => {
+                    use async_compression::tokio::bufread::GzipEncoder;
+
+                    builder = builder.header("Content-Encoding", "gzip");
+                    Body::wrap_stream(FramedRead::new(
+                        GzipEncoder::new(BufReader::new(file)),
+                        BytesCodec::new(),
+                    ))
+                }
+                Some(Encoding::Deflate) => {
+                    use async_compression::tokio::bufread::DeflateEncoder;
+
+                    builder = builder.header("Content-Encoding", "deflate");
+                    Body::wrap_stream(FramedRead::new(
+                        DeflateEncoder::new(BufReader::new(file)),
+                        BytesCodec::new(),
+                    ))
+                }
+                None => {
+                    builder = builder.header("Content-Length", len);
+                    Body::wrap_stream(FramedRead::new(file, BytesCodec::new()))
+                }
+            };
+
+            builder.body(body)
+        }
+    }
+}
+
+/// Reads `path` as Markdown and responds with the rendered HTML.
+async fn render_markdown(path: &std::path::Path) -> Result<Response<Body>, HttpError> {
+    use pulldown_cmark::{html, Options, Parser};
+
+    let source = match tokio::fs::read_to_string(path).await {
+        Ok(source) => source,
+        Err(_) => {
+            return Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::empty())
+        }
+    };
+
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_FOOTNOTES);
+
+    let mut rendered = String::new();
+    html::push_html(&mut rendered, Parser::new_ext(&source, options));
+
+    let page = format!(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"></head>\n<body>\n{rendered}</body>\n</html>\n"
+    );
+
+    Response::builder()
+        .header("Content-Type", "text/html; charset=utf-8")
+        .body(Body::from(page))
+}
+
+/// Renders an HTML directory listing for `dir`, linking relative to `request_path`.
+async fn render_autoindex(dir: &std::path::Path, request_path: &str) -> Result<Response<Body>, HttpError> {
+    use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+    use tokio::fs::read_dir;
+
+    let mut entries = match read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(_) => {
+            return Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::empty())
+        }
+    };
+
+    let mut listing = Vec::new();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let is_dir = entry.file_type().await.map(|t| t.is_dir()).unwrap_or(false);
+        if let Ok(name) = entry.file_name().into_string() {
+            listing.push((name, is_dir));
+        }
+    }
+    listing.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let base = if request_path.ends_with('/') {
+        request_path.to_owned()
     } else {
-        Err(StatusCode::NOT_FOUND)
+        format!("{}/", request_path)
     };
+    let base = escape_html(&base);
 
-    match res {
-        Ok(body) => Response::builder().body(body),
-        Err(status) => Response::builder().status(status).body(Body::empty()),
+    let mut html = String::from("<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"></head>\n<body>\n<ul>\n");
+    for (name, is_dir) in listing {
+        let encoded = utf8_percent_encode(&name, NON_ALPHANUMERIC).to_string();
+        let name = escape_html(&name);
+        let suffix = if is_dir { "/" } else { "" };
+        html.push_str(&format!(
+            "<li><a href=\"{base}{encoded}{suffix}\">{name}{suffix}</a></li>\n"
+        ));
+    }
+    html.push_str("</ul>\n</body>\n</html>\n");
+
+    Response::builder()
+        .header("Content-Type", "text/html; charset=utf-8")
+        .body(Body::from(html))
+}
+
+/// Escapes the characters HTML requires escaping in text nodes and
+/// (double-quoted) attribute values: `&`, `<`, `>`, `"`, and `'`.
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A content coding negotiated from the request's `Accept-Encoding` header.
+enum Encoding {
+    Gzip,
+    Deflate,
+}
+
+/// Picks a response encoding from an `Accept-Encoding` header value.
+///
+/// This is a simple membership check rather than full quality-value
+/// negotiation; `gzip` is preferred over `deflate` when both are offered.
+fn negotiate_encoding(header: &str) -> Option<Encoding> {
+    let offered: Vec<&str> = header
+        .split(',')
+        .filter_map(|coding| {
+            let mut parts = coding.split(';');
+            let name = parts.next()?.trim();
+
+            let rejected = parts.any(|param| {
+                param
+                    .trim()
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .is_some_and(|q| q <= 0.0)
+            });
+
+            if rejected {
+                None
+            } else {
+                Some(name)
+            }
+        })
+        .collect();
+
+    if offered.contains(&"gzip") {
+        Some(Encoding::Gzip)
+    } else if offered.contains(&"deflate") {
+        Some(Encoding::Deflate)
+    } else {
+        None
+    }
+}
+
+/// Whether a file of the given MIME type is worth compressing.
+///
+/// Formats that are already compressed (images, audio, video, archives)
+/// gain nothing from another compression pass.
+fn should_compress(mime: &mime_guess::Mime) -> bool {
+    !matches!(
+        mime.type_(),
+        mime_guess::mime::IMAGE | mime_guess::mime::AUDIO | mime_guess::mime::VIDEO
+    ) && !matches!(
+        mime.subtype().as_str(),
+        "zip" | "gzip" | "x-gzip" | "x-7z-compressed" | "x-rar-compressed" | "x-bzip"
+            | "x-bzip2" | "x-xz" | "zstd"
+    )
+}
+
+/// The outcome of parsing a `Range` header against a known content length.
+enum Range {
+    /// No range was requested, or the header couldn't be honored as a single
+    /// range (multi-range, unknown unit, malformed syntax); serve the whole
+    /// file with a normal `200 OK`.
+    Full,
+    /// A single byte range that fits within the file, as an inclusive `[start, end]` pair.
+    Satisfiable { start: u64, end: u64 },
+    /// The requested range starts at or beyond the end of the file, or its
+    /// start lies past its end.
+    Unsatisfiable,
+}
+
+/// Parses a `Range: bytes=start-end` header.
+///
+/// Only a single `bytes=` range is supported; `start-`, `-suffix_len` and
+/// `start-end` forms are recognized. Anything else (multiple ranges, other
+/// units, malformed syntax) falls back to [`Range::Full`].
+fn parse_range(header: &str, len: u64) -> Range {
+    let spec = match header.strip_prefix("bytes=") {
+        Some(spec) if !spec.contains(',') => spec,
+        _ => return Range::Full,
+    };
+
+    let (start, end) = match spec.split_once('-') {
+        Some(("", suffix)) => match suffix.parse::<u64>() {
+            Ok(suffix_len) if suffix_len > 0 => {
+                (len.saturating_sub(suffix_len), len.saturating_sub(1))
+            }
+            _ => return Range::Full,
+        },
+        Some((start, "")) => match start.parse::<u64>() {
+            Ok(start) => (start, len.saturating_sub(1)),
+            Err(_) => return Range::Full,
+        },
+        Some((start, end)) => match (start.parse::<u64>(), end.parse::<u64>()) {
+            (Ok(start), Ok(end)) => (start, end.min(len.saturating_sub(1))),
+            _ => return Range::Full,
+        },
+        None => return Range::Full,
+    };
+
+    if len == 0 || start >= len || start > end {
+        Range::Unsatisfiable
+    } else {
+        Range::Satisfiable { start, end }
+    }
+}
+
+/// Strips a trailing `:port` from a `Host` header value. IPv6 literals are
+/// bracketed (e.g. `[::1]:8080`), so the port is only stripped past the
+/// closing bracket; otherwise plain hosts are cut at the last colon.
+fn strip_port(host: &str) -> &str {
+    if host.starts_with('[') {
+        return match host.find(']') {
+            Some(end) => &host[..=end],
+            None => host,
+        };
+    }
+
+    match host.rfind(':') {
+        Some(idx) => &host[..idx],
+        None => host,
     }
 }
 
@@ -105,3 +681,141 @@ fn normalize(uri: &str) -> PathBuf {
 
     normalized
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_range_full_file() {
+        match parse_range("bytes=0-99", 100) {
+            Range::Satisfiable { start, end } => assert_eq!((start, end), (0, 99)),
+            _ => panic!("expected a satisfiable range"),
+        }
+    }
+
+    #[test]
+    fn parse_range_open_ended() {
+        match parse_range("bytes=50-", 100) {
+            Range::Satisfiable { start, end } => assert_eq!((start, end), (50, 99)),
+            _ => panic!("expected a satisfiable range"),
+        }
+    }
+
+    #[test]
+    fn parse_range_suffix() {
+        match parse_range("bytes=-10", 100) {
+            Range::Satisfiable { start, end } => assert_eq!((start, end), (90, 99)),
+            _ => panic!("expected a satisfiable range"),
+        }
+    }
+
+    #[test]
+    fn parse_range_suffix_longer_than_file_clamps_to_start() {
+        match parse_range("bytes=-1000", 100) {
+            Range::Satisfiable { start, end } => assert_eq!((start, end), (0, 99)),
+            _ => panic!("expected a satisfiable range"),
+        }
+    }
+
+    #[test]
+    fn parse_range_end_clamped_to_file_length() {
+        match parse_range("bytes=0-1000", 100) {
+            Range::Satisfiable { start, end } => assert_eq!((start, end), (0, 99)),
+            _ => panic!("expected a satisfiable range"),
+        }
+    }
+
+    #[test]
+    fn parse_range_start_at_eof_is_unsatisfiable() {
+        assert!(matches!(
+            parse_range("bytes=100-", 100),
+            Range::Unsatisfiable
+        ));
+    }
+
+    #[test]
+    fn parse_range_start_past_eof_is_unsatisfiable() {
+        assert!(matches!(
+            parse_range("bytes=500-600", 100),
+            Range::Unsatisfiable
+        ));
+    }
+
+    #[test]
+    fn parse_range_start_after_end_is_unsatisfiable() {
+        assert!(matches!(
+            parse_range("bytes=50-10", 100),
+            Range::Unsatisfiable
+        ));
+    }
+
+    #[test]
+    fn parse_range_empty_file_is_unsatisfiable() {
+        assert!(matches!(parse_range("bytes=0-0", 0), Range::Unsatisfiable));
+    }
+
+    #[test]
+    fn parse_range_multi_range_falls_back_to_full() {
+        assert!(matches!(parse_range("bytes=0-10,20-30", 100), Range::Full));
+    }
+
+    #[test]
+    fn parse_range_malformed_falls_back_to_full() {
+        assert!(matches!(parse_range("bytes=abc", 100), Range::Full));
+    }
+
+    #[test]
+    fn parse_range_unknown_unit_falls_back_to_full() {
+        assert!(matches!(parse_range("items=0-10", 100), Range::Full));
+    }
+
+    #[test]
+    fn negotiate_encoding_prefers_gzip() {
+        assert!(matches!(
+            negotiate_encoding("gzip, deflate"),
+            Some(Encoding::Gzip)
+        ));
+    }
+
+    #[test]
+    fn negotiate_encoding_falls_back_to_deflate() {
+        assert!(matches!(
+            negotiate_encoding("deflate"),
+            Some(Encoding::Deflate)
+        ));
+    }
+
+    #[test]
+    fn negotiate_encoding_respects_q_zero() {
+        assert!(matches!(
+            negotiate_encoding("gzip;q=0, deflate"),
+            Some(Encoding::Deflate)
+        ));
+    }
+
+    #[test]
+    fn negotiate_encoding_none_offered() {
+        assert!(negotiate_encoding("br").is_none());
+    }
+
+    #[test]
+    fn strip_port_removes_plain_host_port() {
+        assert_eq!(strip_port("example.com:8080"), "example.com");
+    }
+
+    #[test]
+    fn strip_port_leaves_bare_host_unchanged() {
+        assert_eq!(strip_port("example.com"), "example.com");
+    }
+
+    #[test]
+    fn strip_port_keeps_ipv6_literal_brackets() {
+        assert_eq!(strip_port("[::1]:8080"), "[::1]");
+    }
+
+    #[test]
+    fn strip_port_leaves_bare_ipv6_literal_unchanged() {
+        assert_eq!(strip_port("[::1]"), "[::1]");
+    }
+}